@@ -0,0 +1,17 @@
+import { type Address, getProgramDerivedAddress, getU64Encoder } from 'gill'
+import { SOLIGNITION_PROGRAM_ADDRESS } from '../client/js'
+
+export interface GetLoanAddressConfig {
+  loanId: bigint | number
+  programAddress?: Address
+}
+
+// Loan IDs are assigned on-chain from `ProtocolConfig.loanCounter`, so the address of the
+// next loan is the loan PDA for the current counter value.
+export async function getLoanAddress(config: GetLoanAddressConfig): Promise<Address> {
+  const [loanAddress] = await getProgramDerivedAddress({
+    programAddress: config.programAddress ?? SOLIGNITION_PROGRAM_ADDRESS,
+    seeds: [new TextEncoder().encode('loan'), getU64Encoder().encode(config.loanId)],
+  })
+  return loanAddress
+}
@@ -1,7 +1,13 @@
 // Here we export some useful types and functions for interacting with the Anchor program.
 import { Account, getBase58Decoder, SolanaClient } from 'gill'
 import { getProgramAccountsDecoded } from './helpers/get-program-accounts-decoded'
-import { Solignition, SOLIGNITION_DISCRIMINATOR, SOLIGNITION_PROGRAM_ADDRESS, getSolignitionDecoder } from './client/js'
+import {
+  LoanState,
+  Solignition,
+  SOLIGNITION_DISCRIMINATOR,
+  SOLIGNITION_PROGRAM_ADDRESS,
+  getSolignitionDecoder,
+} from './client/js'
 import SolignitionIDL from '../target/idl/solignition.json'
 
 export type SolignitionAccount = Account<Solignition, string>
@@ -10,6 +16,7 @@ export type SolignitionAccount = Account<Solignition, string>
 export { SolignitionIDL }
 
 export * from './client/js'
+export * from './helpers/get-loan-address'
 
 export function getSolignitionProgramAccounts(rpc: SolanaClient['rpc']) {
   return getProgramAccountsDecoded(rpc, {
@@ -18,3 +25,11 @@ export function getSolignitionProgramAccounts(rpc: SolanaClient['rpc']) {
     programAddress: SOLIGNITION_PROGRAM_ADDRESS,
   })
 }
+
+// Loan states whose principal is still lent out
+export const OUTSTANDING_LOAN_STATES: LoanState[] = [
+  LoanState.PendingDeployment,
+  LoanState.Deployed,
+  LoanState.Overdue,
+  LoanState.Defaulted,
+]
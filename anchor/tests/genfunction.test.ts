@@ -1,7 +1,7 @@
 import * as anchor from '@coral-xyz/anchor';
 import { Program } from '@coral-xyz/anchor';
 import { Solignition } from "../target/types/solignition";
-import { PublicKey, SystemProgram, Keypair, LAMPORTS_PER_SOL } from "@solana/web3.js";
+import { PublicKey, SystemProgram, Keypair, LAMPORTS_PER_SOL, SYSVAR_RENT_PUBKEY } from "@solana/web3.js";
 import { assert, expect } from "chai";
 import { createKeyPairFromBytes, createSignerFromKeyPair, getBase58Encoder } from 'gill';
 import { loadKeypairSignerFromFile, type KeyPairSigner } from 'gill/node';
@@ -32,6 +32,11 @@ describe("solignition", () => {
   let treasuryPda: PublicKey;
   let depositor1RecordPda: PublicKey;
   let depositor2RecordPda: PublicKey;
+  let shareMintPda: PublicKey;
+  let shareMetadataPda: PublicKey;
+  let depositor1ShareAccount: PublicKey;
+  let depositor2ShareAccount: PublicKey;
+  let termSchedulePda: PublicKey;
 
   // Constants
   const VAULT_SEED = Buffer.from("vault");
@@ -41,6 +46,20 @@ describe("solignition", () => {
   const LOAN_SEED = Buffer.from("loan");
   const DEPOSITOR_SEED = Buffer.from("depositor");
   const PROTOCOL_CONFIG_SEED = Buffer.from("config");
+  const BPF_LOADER_UPGRADEABLE_PROGRAM_ID = new PublicKey("BPFLoaderUpgradeab1e11111111111111111111111");
+  const SHARE_MINT_SEED = Buffer.from("share_mint");
+  const TERM_SCHEDULE_SEED = Buffer.from("term_schedule");
+  const TOKEN_METADATA_PROGRAM_ID = new PublicKey("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+  const TOKEN_PROGRAM_ID = anchor.utils.token.TOKEN_PROGRAM_ID;
+  const ASSOCIATED_TOKEN_PROGRAM_ID = anchor.utils.token.ASSOCIATED_PROGRAM_ID;
+
+  // Flat 5% curve so loan quotes don't move with utilization
+  const RATE_MODEL = {
+    baseRateBps: 500,
+    slope1Bps: 0,
+    optimalUtilizationBps: 8000,
+    slope2Bps: 0,
+  };
 
   before(async () => {
     //test public keypair seed don't use on mainnet
@@ -116,16 +135,71 @@ describe("solignition", () => {
       [DEPOSITOR_SEED, depositor2.publicKey.toBuffer()],
       program.programId
     );
+
+    [shareMintPda] = PublicKey.findProgramAddressSync(
+      [SHARE_MINT_SEED],
+      program.programId
+    );
+
+    [shareMetadataPda] = PublicKey.findProgramAddressSync(
+      [Buffer.from("metadata"), TOKEN_METADATA_PROGRAM_ID.toBuffer(), shareMintPda.toBuffer()],
+      TOKEN_METADATA_PROGRAM_ID
+    );
+
+    [termSchedulePda] = PublicKey.findProgramAddressSync(
+      [TERM_SCHEDULE_SEED],
+      program.programId
+    );
+
+    depositor1ShareAccount = anchor.utils.token.associatedAddress({
+      mint: shareMintPda,
+      owner: depositor1.publicKey,
+    });
+
+    depositor2ShareAccount = anchor.utils.token.associatedAddress({
+      mint: shareMintPda,
+      owner: depositor2.publicKey,
+    });
   });
 
+  const shareBalance = async (shareAccount: PublicKey) => {
+    const balance = await connection.getTokenAccountBalance(shareAccount);
+    return Number(balance.value.amount);
+  };
+
+  // Loan IDs are assigned from the on-chain loan counter
+  const nextLoan = async () => {
+    const config = await program.account.protocolConfig.fetch(protocolConfigPda);
+    const loanId = config.loanCounter.toNumber();
+    const [loanPda] = PublicKey.findProgramAddressSync(
+      [LOAN_SEED, new anchor.BN(loanId).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    return { loanId, loanPda };
+  };
+
+  const programDataFor = (programId: PublicKey) => {
+    const [programData] = PublicKey.findProgramAddressSync(
+      [programId.toBuffer()],
+      BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+    );
+    return programData;
+  };
+
   describe("initialize", () => {
     it("should initialize the protocol successfully", async () => {
       const adminFeeSplitBps = 5000; // 50% to depositors, 50% to treasury
-      const defaultInterestRateBps = 500; // 5%
       const defaultAdminFeeBps = 100; // 1%
 
       const tx = await program.methods
-        .initialize(adminFeeSplitBps, defaultInterestRateBps, defaultAdminFeeBps)
+        .initialize(
+          adminFeeSplitBps,
+          RATE_MODEL,
+          defaultAdminFeeBps,
+          "Solignition Vault Share",
+          "sSOL",
+          ""
+        )
         .accounts({
           admin: admin.publicKey,
           protocolConfig: protocolConfigPda,
@@ -134,7 +208,12 @@ describe("solignition", () => {
           adminPda: adminPda,
           treasury: treasuryPda,
           deployer: deployer.publicKey,
+          shareMint: shareMintPda,
+          shareMetadata: shareMetadataPda,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
+          rent: SYSVAR_RENT_PUBKEY,
         })
         .signers([admin])
         .rpc();
@@ -147,17 +226,21 @@ describe("solignition", () => {
       assert.ok(config.treasury.equals(treasuryPda));
       assert.ok(config.deployer.equals(deployer.publicKey));
       assert.equal(config.adminFeeSplitBps, adminFeeSplitBps);
-      assert.equal(config.defaultInterestRateBps, defaultInterestRateBps);
+      assert.deepEqual(config.rateModel, RATE_MODEL);
       assert.equal(config.defaultAdminFeeBps, defaultAdminFeeBps);
       assert.equal(config.totalDeposits.toNumber(), 0);
       assert.equal(config.totalLoansOutstanding.toNumber(), 0);
       assert.equal(config.isPaused, false);
+
+      // Verify the share mint is owned by the token program
+      const shareMint = await connection.getAccountInfo(shareMintPda);
+      assert.ok(shareMint.owner.equals(TOKEN_PROGRAM_ID));
     });
 
     it("should fail to initialize twice", async () => {
       try {
         await program.methods
-          .initialize(5000, 500, 100)
+          .initialize(5000, RATE_MODEL, 100, "Solignition Vault Share", "sSOL", "")
           .accounts({
             admin: admin.publicKey,
             protocolConfig: protocolConfigPda,
@@ -166,7 +249,12 @@ describe("solignition", () => {
             adminPda: adminPda,
             treasury: treasuryPda,
             deployer: deployer.publicKey,
+            shareMint: shareMintPda,
+            shareMetadata: shareMetadataPda,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            tokenMetadataProgram: TOKEN_METADATA_PROGRAM_ID,
             systemProgram: SystemProgram.programId,
+            rent: SYSVAR_RENT_PUBKEY,
           })
           .signers([admin])
           .rpc();
@@ -175,6 +263,33 @@ describe("solignition", () => {
         assert.ok(error.toString().includes("already in use"));
       }
     });
+
+    it("should allow admin to set the term schedule", async () => {
+      // One bracket covering every loan term used below
+      const brackets = [
+        {
+          minDuration: new anchor.BN(1),
+          maxDuration: new anchor.BN(365 * 24 * 60 * 60),
+          interestPremiumBps: 0,
+          adminFeeBps: 100, // 1%
+          maxPrincipal: new anchor.BN(0), // Uncapped
+        },
+      ];
+
+      await program.methods
+        .setTermSchedule(brackets)
+        .accounts({
+          admin: admin.publicKey,
+          protocolConfig: protocolConfigPda,
+          termSchedule: termSchedulePda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([admin])
+        .rpc();
+
+      const termSchedule = await program.account.termSchedule.fetch(termSchedulePda);
+      assert.equal(termSchedule.brackets[0].adminFeeBps, 100);
+    });
   });
 
   describe("deposit", () => {
@@ -185,12 +300,17 @@ describe("solignition", () => {
       const depositorBalanceBefore = await connection.getBalance(depositor1.publicKey);
 
       const tx = await program.methods
-        .deposit(depositAmount)
+        .deposit(depositAmount, null)
         .accounts({
           depositor: depositor1.publicKey,
           depositorRecord: depositor1RecordPda,
           protocolConfig: protocolConfigPda,
           vault: vaultPda,
+          shareMint: shareMintPda,
+          depositorShareAccount: depositor1ShareAccount,
+          position: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
         .signers([depositor1])
@@ -210,6 +330,9 @@ describe("solignition", () => {
       assert.equal(depositorRecord.depositedAmount.toNumber(), depositAmount.toNumber());
       assert.equal(depositorRecord.shareAmount.toNumber(), depositAmount.toNumber());
 
+      // Verify share tokens minted to the depositor
+      assert.equal(await shareBalance(depositor1ShareAccount), depositAmount.toNumber());
+
       // Verify protocol config updated
       const config = await program.account.protocolConfig.fetch(protocolConfigPda);
       assert.equal(config.totalDeposits.toNumber(), depositAmount.toNumber());
@@ -219,12 +342,17 @@ describe("solignition", () => {
       const depositAmount = new anchor.BN(5 * LAMPORTS_PER_SOL);
 
       await program.methods
-        .deposit(depositAmount)
+        .deposit(depositAmount, null)
         .accounts({
           depositor: depositor1.publicKey,
           depositorRecord: depositor1RecordPda,
           protocolConfig: protocolConfigPda,
           vault: vaultPda,
+          shareMint: shareMintPda,
+          depositorShareAccount: depositor1ShareAccount,
+          position: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
         .signers([depositor1])
@@ -244,12 +372,17 @@ describe("solignition", () => {
       const depositAmount = new anchor.BN(20 * LAMPORTS_PER_SOL);
 
       await program.methods
-        .deposit(depositAmount)
+        .deposit(depositAmount, null)
         .accounts({
           depositor: depositor2.publicKey,
           depositorRecord: depositor2RecordPda,
           protocolConfig: protocolConfigPda,
           vault: vaultPda,
+          shareMint: shareMintPda,
+          depositorShareAccount: depositor2ShareAccount,
+          position: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
         .signers([depositor2])
@@ -266,12 +399,17 @@ describe("solignition", () => {
     it("should fail to deposit zero amount", async () => {
       try {
         await program.methods
-          .deposit(new anchor.BN(0))
+          .deposit(new anchor.BN(0), null)
           .accounts({
             depositor: depositor1.publicKey,
             depositorRecord: depositor1RecordPda,
             protocolConfig: protocolConfigPda,
             vault: vaultPda,
+            shareMint: shareMintPda,
+            depositorShareAccount: depositor1ShareAccount,
+            position: null,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
             systemProgram: SystemProgram.programId,
           })
           .signers([depositor1])
@@ -285,18 +423,24 @@ describe("solignition", () => {
 
   describe("withdraw", () => {
     it("should allow depositor to withdraw SOL", async () => {
-      const withdrawAmount = new anchor.BN(5 * LAMPORTS_PER_SOL);
+      // No yield has accrued yet, so shares still redeem 1:1 for lamports
+      const withdrawShares = new anchor.BN(5 * LAMPORTS_PER_SOL);
 
       const vaultBalanceBefore = await connection.getBalance(vaultPda);
       const depositorBalanceBefore = await connection.getBalance(depositor1.publicKey);
 
       const tx = await program.methods
-        .withdraw(withdrawAmount)
+        .withdraw(withdrawShares, false)
         .accounts({
           depositor: depositor1.publicKey,
           depositorRecord: depositor1RecordPda,
           protocolConfig: protocolConfigPda,
           vault: vaultPda,
+          shareMint: shareMintPda,
+          depositorShareAccount: depositor1ShareAccount,
+          position: null,
+          treasury: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
         .signers([depositor1])
@@ -306,7 +450,7 @@ describe("solignition", () => {
 
       // Verify vault balance decreased
       const vaultBalanceAfter = await connection.getBalance(vaultPda);
-      assert.equal(vaultBalanceBefore - vaultBalanceAfter, withdrawAmount.toNumber());
+      assert.equal(vaultBalanceBefore - vaultBalanceAfter, withdrawShares.toNumber());
 
       // Verify depositor record updated
       const depositorRecord = await program.account.depositorRecord.fetch(
@@ -316,38 +460,51 @@ describe("solignition", () => {
         depositorRecord.shareAmount.toNumber(),
         10 * LAMPORTS_PER_SOL
       );
+
+      // Verify share tokens burned
+      assert.equal(await shareBalance(depositor1ShareAccount), 10 * LAMPORTS_PER_SOL);
     });
 
-    it("should fail to withdraw more than balance", async () => {
-      const withdrawAmount = new anchor.BN(100 * LAMPORTS_PER_SOL);
+    it("should fail to withdraw more than available liquidity", async () => {
+      const withdrawShares = new anchor.BN(100 * LAMPORTS_PER_SOL);
 
       try {
         await program.methods
-          .withdraw(withdrawAmount)
+          .withdraw(withdrawShares, false)
           .accounts({
             depositor: depositor1.publicKey,
             depositorRecord: depositor1RecordPda,
             protocolConfig: protocolConfigPda,
             vault: vaultPda,
+            shareMint: shareMintPda,
+            depositorShareAccount: depositor1ShareAccount,
+            position: null,
+            treasury: null,
+            tokenProgram: TOKEN_PROGRAM_ID,
             systemProgram: SystemProgram.programId,
           })
           .signers([depositor1])
           .rpc();
         assert.fail("Should have thrown error");
       } catch (error) {
-        assert.ok(error.toString().includes("InsufficientBalance"));
+        assert.ok(error.toString().includes("InsufficientLiquidity"));
       }
     });
 
     it("should fail if wrong depositor tries to withdraw", async () => {
       try {
         await program.methods
-          .withdraw(new anchor.BN(1 * LAMPORTS_PER_SOL))
+          .withdraw(new anchor.BN(1 * LAMPORTS_PER_SOL), false)
           .accounts({
             depositor: depositor2.publicKey,
             depositorRecord: depositor1RecordPda,
             protocolConfig: protocolConfigPda,
             vault: vaultPda,
+            shareMint: shareMintPda,
+            depositorShareAccount: depositor2ShareAccount,
+            position: null,
+            treasury: null,
+            tokenProgram: TOKEN_PROGRAM_ID,
             systemProgram: SystemProgram.programId,
           })
           .signers([depositor2])
@@ -369,17 +526,14 @@ describe("solignition", () => {
     let loanId: number;
     let loanPda: PublicKey;
 
-    before(() => {
-      loanId = 1;
-      [loanPda] = PublicKey.findProgramAddressSync(
-        [LOAN_SEED, new anchor.BN(loanId).toArrayLike(Buffer, "le", 8)],
-        program.programId
-      );
+    before(async () => {
+      ({ loanId, loanPda } = await nextLoan());
     });
 
     it("should allow borrower to request a loan", async () => {
       const principal = new anchor.BN(5 * LAMPORTS_PER_SOL);
       const duration = new anchor.BN(30 * 24 * 60 * 60); // 30 days in seconds
+      // Quoted on-chain; the borrower only caps what they will accept
       const interestRateBps = 500; // 5%
       const adminFeeBps = 100; // 1%
 
@@ -388,16 +542,18 @@ describe("solignition", () => {
 
       const tx = await program.methods
         .requestLoan(
-          new anchor.BN(loanId),
           principal,
           duration,
           interestRateBps,
-          adminFeeBps
+          adminFeeBps,
+          { simple: {} },
+          { act365: {} }
         )
         .accounts({
           borrower: borrower.publicKey,
           loan: loanPda,
           protocolConfig: protocolConfigPda,
+          termSchedule: termSchedulePda,
           vault: vaultPda,
           authorityPda: authorityPda,
           adminPda: adminPda,
@@ -417,7 +573,9 @@ describe("solignition", () => {
       assert.equal(loan.duration.toNumber(), duration.toNumber());
       assert.equal(loan.interestRateBps, interestRateBps);
       assert.equal(loan.adminFeeBps, adminFeeBps);
-      assert.deepEqual(loan.state, { active: {} });
+      assert.deepEqual(loan.interestMode, { simple: {} });
+      assert.deepEqual(loan.dayCount, { act365: {} });
+      assert.deepEqual(loan.state, { pendingDeployment: {} });
 
       // Verify admin fee paid
       const adminFee = principal.toNumber() * adminFeeBps / 10000;
@@ -428,26 +586,56 @@ describe("solignition", () => {
       assert.equal(config.totalLoansOutstanding.toNumber(), principal.toNumber());
     });
 
+    it("should fail when the quote exceeds the borrower's maximum", async () => {
+      const { loanPda: cappedLoanPda } = await nextLoan();
+
+      try {
+        await program.methods
+          .requestLoan(
+            new anchor.BN(1 * LAMPORTS_PER_SOL),
+            new anchor.BN(30 * 24 * 60 * 60),
+            0, // Below any quoted rate
+            100,
+            { simple: {} },
+            { act365: {} }
+          )
+          .accounts({
+            borrower: borrower.publicKey,
+            loan: cappedLoanPda,
+            protocolConfig: protocolConfigPda,
+            termSchedule: termSchedulePda,
+            vault: vaultPda,
+            authorityPda: authorityPda,
+            adminPda: adminPda,
+            deployerPda: deployer.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([borrower])
+          .rpc();
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.ok(error.toString().includes("QuoteExceedsMaximum"));
+      }
+    });
+
     it("should fail to request loan with zero principal", async () => {
-      const loanId2 = 2;
-      const [loanPda2] = PublicKey.findProgramAddressSync(
-        [LOAN_SEED, new anchor.BN(loanId2).toArrayLike(Buffer, "le", 8)],
-        program.programId
-      );
+      const { loanPda: loanPda2 } = await nextLoan();
 
       try {
         await program.methods
           .requestLoan(
-            new anchor.BN(loanId2),
             new anchor.BN(0),
             new anchor.BN(30 * 24 * 60 * 60),
             500,
-            100
+            100,
+            { simple: {} },
+            { act365: {} }
           )
           .accounts({
             borrower: borrower.publicKey,
             loan: loanPda2,
             protocolConfig: protocolConfigPda,
+            termSchedule: termSchedulePda,
             vault: vaultPda,
             authorityPda: authorityPda,
             adminPda: adminPda,
@@ -462,26 +650,56 @@ describe("solignition", () => {
       }
     });
 
+    it("should fail for a duration outside the term schedule", async () => {
+      const { loanPda: unpricedLoanPda } = await nextLoan();
+
+      try {
+        await program.methods
+          .requestLoan(
+            new anchor.BN(1 * LAMPORTS_PER_SOL),
+            new anchor.BN(2 * 365 * 24 * 60 * 60), // Past the last bracket
+            500,
+            100,
+            { simple: {} },
+            { act365: {} }
+          )
+          .accounts({
+            borrower: borrower.publicKey,
+            loan: unpricedLoanPda,
+            protocolConfig: protocolConfigPda,
+            termSchedule: termSchedulePda,
+            vault: vaultPda,
+            authorityPda: authorityPda,
+            adminPda: adminPda,
+            deployerPda: deployer.publicKey,
+            systemProgram: SystemProgram.programId,
+          })
+          .signers([borrower])
+          .rpc();
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.ok(error.toString().includes("DurationOutsideTermSchedule"));
+      }
+    });
+
     it("should fail to request loan exceeding liquidity", async () => {
-      const loanId3 = 3;
-      const [loanPda3] = PublicKey.findProgramAddressSync(
-        [LOAN_SEED, new anchor.BN(loanId3).toArrayLike(Buffer, "le", 8)],
-        program.programId
-      );
+      const { loanPda: loanPda3 } = await nextLoan();
 
       try {
         await program.methods
           .requestLoan(
-            new anchor.BN(loanId3),
             new anchor.BN(100 * LAMPORTS_PER_SOL), // More than available
             new anchor.BN(30 * 24 * 60 * 60),
             500,
-            100
+            100,
+            { simple: {} },
+            { act365: {} }
           )
           .accounts({
             borrower: borrower.publicKey,
             loan: loanPda3,
             protocolConfig: protocolConfigPda,
+            termSchedule: termSchedulePda,
             vault: vaultPda,
             authorityPda: authorityPda,
             adminPda: adminPda,
@@ -503,7 +721,7 @@ describe("solignition", () => {
     let programPubkey: PublicKey;
 
     before(() => {
-      loanId = 1;
+      loanId = 0; // The first loan issued by the protocol
       [loanPda] = PublicKey.findProgramAddressSync(
         [LOAN_SEED, new anchor.BN(loanId).toArrayLike(Buffer, "le", 8)],
         program.programId
@@ -511,45 +729,50 @@ describe("solignition", () => {
       programPubkey = Keypair.generate().publicKey;
     });
 
-    it("should allow admin to set deployed program pubkey", async () => {
-      const tx = await program.methods
-        .setDeployedProgram(new anchor.BN(loanId), programPubkey)
-        .accounts({
-          admin: admin.publicKey,
-          protocolConfig: protocolConfigPda,
-          loan: loanPda,
-        })
-        .signers([admin])
-        .rpc();
-
-      console.log("Set deployed program tx:", tx);
+    it("should reject a program that is not upgradeable", async () => {
+      // The program's upgrade authority is verified on-chain, so an arbitrary key is refused
+      try {
+        await program.methods
+          .setDeployedProgram(new anchor.BN(loanId))
+          .accounts({
+            admin: admin.publicKey,
+            protocolConfig: protocolConfigPda,
+            loan: loanPda,
+            program: programPubkey,
+            programData: programDataFor(programPubkey),
+            authorityPda: authorityPda,
+          })
+          .signers([admin])
+          .rpc();
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.ok(error.toString().includes("ProgramNotUpgradeable"));
+      }
 
-      // Verify loan updated
+      // Verify loan still awaits deployment
       const loan = await program.account.loan.fetch(loanPda);
-      assert.ok(loan.programPubkey.equals(programPubkey));
+      assert.ok(loan.programPubkey.equals(PublicKey.default));
     });
 
     it("should fail if non-admin tries to set program", async () => {
-      const loanId4 = 4;
       const principal = new anchor.BN(2 * LAMPORTS_PER_SOL);
-      const [loanPda4] = PublicKey.findProgramAddressSync(
-        [LOAN_SEED, new anchor.BN(loanId4).toArrayLike(Buffer, "le", 8)],
-        program.programId
-      );
+      const { loanId: pendingLoanId, loanPda: pendingLoanPda } = await nextLoan();
 
       // First create a new loan
       await program.methods
         .requestLoan(
-          new anchor.BN(loanId4),
           principal,
           new anchor.BN(30 * 24 * 60 * 60),
           500,
-          100
+          100,
+          { simple: {} },
+          { act365: {} }
         )
         .accounts({
           borrower: borrower.publicKey,
-          loan: loanPda4,
+          loan: pendingLoanPda,
           protocolConfig: protocolConfigPda,
+          termSchedule: termSchedulePda,
           vault: vaultPda,
           authorityPda: authorityPda,
           adminPda: adminPda,
@@ -559,13 +782,18 @@ describe("solignition", () => {
         .signers([borrower])
         .rpc();
 
+      const otherProgram = Keypair.generate().publicKey;
+
       try {
         await program.methods
-          .setDeployedProgram(new anchor.BN(loanId4), Keypair.generate().publicKey)
+          .setDeployedProgram(new anchor.BN(pendingLoanId))
           .accounts({
             admin: depositor1.publicKey,
             protocolConfig: protocolConfigPda,
-            loan: loanPda4,
+            loan: pendingLoanPda,
+            program: otherProgram,
+            programData: programDataFor(otherProgram),
+            authorityPda: authorityPda,
           })
           .signers([depositor1])
           .rpc();
@@ -582,7 +810,7 @@ describe("solignition", () => {
     let programDataAccount: PublicKey;
 
     before(() => {
-      loanId = 1;
+      loanId = 0; // The first loan issued by the protocol
       [loanPda] = PublicKey.findProgramAddressSync(
         [LOAN_SEED, new anchor.BN(loanId).toArrayLike(Buffer, "le", 8)],
         program.programId
@@ -604,6 +832,7 @@ describe("solignition", () => {
           vault: vaultPda,
           authorityPda: authorityPda,
           programData: programDataAccount,
+          adminPda: adminPda,
           systemProgram: SystemProgram.programId,
         })
         .signers([borrower])
@@ -635,6 +864,7 @@ describe("solignition", () => {
             vault: vaultPda,
             authorityPda: authorityPda,
             programData: programDataAccount,
+            adminPda: adminPda,
             systemProgram: SystemProgram.programId,
           })
           .signers([borrower])
@@ -666,12 +896,17 @@ describe("solignition", () => {
     it("should fail to deposit when paused", async () => {
       try {
         await program.methods
-          .deposit(new anchor.BN(1 * LAMPORTS_PER_SOL))
+          .deposit(new anchor.BN(1 * LAMPORTS_PER_SOL), null)
           .accounts({
             depositor: depositor1.publicKey,
             depositorRecord: depositor1RecordPda,
             protocolConfig: protocolConfigPda,
             vault: vaultPda,
+            shareMint: shareMintPda,
+            depositorShareAccount: depositor1ShareAccount,
+            position: null,
+            tokenProgram: TOKEN_PROGRAM_ID,
+            associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
             systemProgram: SystemProgram.programId,
           })
           .signers([depositor1])
@@ -718,25 +953,23 @@ describe("solignition", () => {
     let expiredLoanPda: PublicKey;
 
     before(async () => {
-      expiredLoanId = 5;
-      [expiredLoanPda] = PublicKey.findProgramAddressSync(
-        [LOAN_SEED, new anchor.BN(expiredLoanId).toArrayLike(Buffer, "le", 8)],
-        program.programId
-      );
+      ({ loanId: expiredLoanId, loanPda: expiredLoanPda } = await nextLoan());
 
       // Create a loan with very short duration for testing
       await program.methods
         .requestLoan(
-          new anchor.BN(expiredLoanId),
           new anchor.BN(1 * LAMPORTS_PER_SOL),
           new anchor.BN(1), // 1 second duration
           500,
-          100
+          100,
+          { simple: {} },
+          { act365: {} }
         )
         .accounts({
           borrower: borrower.publicKey,
           loan: expiredLoanPda,
           protocolConfig: protocolConfigPda,
+          termSchedule: termSchedulePda,
           vault: vaultPda,
           authorityPda: authorityPda,
           adminPda: adminPda,
@@ -759,6 +992,7 @@ describe("solignition", () => {
           admin: admin.publicKey,
           protocolConfig: protocolConfigPda,
           loan: expiredLoanPda,
+          vault: vaultPda,
           adminPda: adminPda,
           treasury: treasuryPda,
           systemProgram: SystemProgram.programId,
@@ -773,34 +1007,32 @@ describe("solignition", () => {
       assert.deepEqual(loan.state, { recovered: {} });
       assert.ok(loan.recoveredTs !== null);
 
-      // Verify protocol config updated
+      // Principal stays outstanding until it is returned or written off
       const config = await program.account.protocolConfig.fetch(protocolConfigPda);
       assert.equal(
         config.totalLoansOutstanding.toNumber(),
-        configBefore.totalLoansOutstanding.toNumber() - loan.principal.toNumber()
+        configBefore.totalLoansOutstanding.toNumber()
       );
     });
 
     it("should fail to recover non-expired loan", async () => {
-      const loanId6 = 6;
-      const [loanPda6] = PublicKey.findProgramAddressSync(
-        [LOAN_SEED, new anchor.BN(loanId6).toArrayLike(Buffer, "le", 8)],
-        program.programId
-      );
+      const { loanPda: longLoanPda } = await nextLoan();
 
       // Create a loan with long duration
       await program.methods
         .requestLoan(
-          new anchor.BN(loanId6),
           new anchor.BN(1 * LAMPORTS_PER_SOL),
           new anchor.BN(365 * 24 * 60 * 60), // 1 year
           500,
-          100
+          100,
+          { simple: {} },
+          { act365: {} }
         )
         .accounts({
           borrower: borrower.publicKey,
-          loan: loanPda6,
+          loan: longLoanPda,
           protocolConfig: protocolConfigPda,
+          termSchedule: termSchedulePda,
           vault: vaultPda,
           authorityPda: authorityPda,
           adminPda: adminPda,
@@ -816,7 +1048,8 @@ describe("solignition", () => {
           .accounts({
             admin: admin.publicKey,
             protocolConfig: protocolConfigPda,
-            loan: loanPda6,
+            loan: longLoanPda,
+            vault: vaultPda,
             adminPda: adminPda,
             treasury: treasuryPda,
             systemProgram: SystemProgram.programId,
@@ -835,7 +1068,7 @@ describe("solignition", () => {
     let recoveredLoanPda: PublicKey;
 
     before(() => {
-      recoveredLoanId = 5; // The expired loan we recovered earlier
+      recoveredLoanId = 2; // The expired loan we recovered earlier
       [recoveredLoanPda] = PublicKey.findProgramAddressSync(
         [LOAN_SEED, new anchor.BN(recoveredLoanId).toArrayLike(Buffer, "le", 8)],
         program.programId
@@ -868,9 +1101,9 @@ describe("solignition", () => {
     });
 
     it("should fail if loan not recovered", async () => {
-      const loanId4 = 4;
-      const [loanPda4] = PublicKey.findProgramAddressSync(
-        [LOAN_SEED, new anchor.BN(loanId4).toArrayLike(Buffer, "le", 8)],
+      const pendingLoanId = 1; // Still pending deployment
+      const [pendingLoanPda] = PublicKey.findProgramAddressSync(
+        [LOAN_SEED, new anchor.BN(pendingLoanId).toArrayLike(Buffer, "le", 8)],
         program.programId
       );
 
@@ -880,7 +1113,7 @@ describe("solignition", () => {
           .accounts({
             caller: admin.publicKey,
             protocolConfig: protocolConfigPda,
-            loan: loanPda4,
+            loan: pendingLoanPda,
             vault: vaultPda,
             deployerPda: deployer.publicKey,
             systemProgram: SystemProgram.programId,
@@ -897,15 +1130,25 @@ describe("solignition", () => {
   describe("update_config", () => {
     it("should allow admin to update configuration", async () => {
       const newAdminFeeSplit = 6000; // 60%
-      const newInterestRate = 600; // 6%
       const newAdminFee = 150; // 1.5%
 
       const tx = await program.methods
         .updateConfig(
           newAdminFeeSplit,
-          newInterestRate,
           newAdminFee,
           null,
+          null,
+          null,
+          null,
+          null,
+          null,
+          null,
+          null,
+          null,
+          null,
+          null,
+          null,
+          null,
           null
         )
         .accounts({
@@ -920,7 +1163,6 @@ describe("solignition", () => {
       // Verify config updated
       const config = await program.account.protocolConfig.fetch(protocolConfigPda);
       assert.equal(config.adminFeeSplitBps, newAdminFeeSplit);
-      assert.equal(config.defaultInterestRateBps, newInterestRate);
       assert.equal(config.defaultAdminFeeBps, newAdminFee);
     });
 
@@ -928,7 +1170,7 @@ describe("solignition", () => {
       const newDeployer = Keypair.generate().publicKey;
 
       await program.methods
-        .updateConfig(null, null, null, newDeployer, null)
+        .updateConfig(null, null, newDeployer, null, null, null, null, null, null, null, null, null, null, null, null, null)
         .accounts({
           admin: admin.publicKey,
           protocolConfig: protocolConfigPda,
@@ -938,12 +1180,22 @@ describe("solignition", () => {
 
       const config = await program.account.protocolConfig.fetch(protocolConfigPda);
       assert.ok(config.deployer.equals(newDeployer));
+
+      // Restore the deployer, since request_loan only pays principal to the configured one
+      await program.methods
+        .updateConfig(null, null, deployer.publicKey, null, null, null, null, null, null, null, null, null, null, null, null, null)
+        .accounts({
+          admin: admin.publicKey,
+          protocolConfig: protocolConfigPda,
+        })
+        .signers([admin])
+        .rpc();
     });
 
     it("should fail if non-admin tries to update config", async () => {
       try {
         await program.methods
-          .updateConfig(5000, null, null, null, null)
+          .updateConfig(5000, null, null, null, null, null, null, null, null, null, null, null, null, null, null, null)
           .accounts({
             admin: depositor1.publicKey,
             protocolConfig: protocolConfigPda,
@@ -956,10 +1208,26 @@ describe("solignition", () => {
       }
     });
 
+    it("should reject a rate model without an optimal utilization", async () => {
+      try {
+        await program.methods
+          .updateRateModel({ ...RATE_MODEL, optimalUtilizationBps: 0 })
+          .accounts({
+            admin: admin.publicKey,
+            protocolConfig: protocolConfigPda,
+          })
+          .signers([admin])
+          .rpc();
+        assert.fail("Should have thrown error");
+      } catch (error) {
+        assert.ok(error.toString().includes("InvalidRateModel"));
+      }
+    });
+
     it("should fail with invalid parameters", async () => {
       try {
         await program.methods
-          .updateConfig(20000, null, null, null, null) // > 10000 bps
+          .updateConfig(20000, null, null, null, null, null, null, null, null, null, null, null, null, null, null, null) // > 10000 bps
           .accounts({
             admin: admin.publicKey,
             protocolConfig: protocolConfigPda,
@@ -975,20 +1243,21 @@ describe("solignition", () => {
 
   describe("integration tests", () => {
     it("should handle full loan lifecycle", async () => {
-      const integrationLoanId = 100;
-      const [integrationLoanPda] = PublicKey.findProgramAddressSync(
-        [LOAN_SEED, new anchor.BN(integrationLoanId).toArrayLike(Buffer, "le", 8)],
-        program.programId
-      );
+      const { loanId: integrationLoanId, loanPda: integrationLoanPda } = await nextLoan();
 
       // 1. Deposit
       await program.methods
-        .deposit(new anchor.BN(10 * LAMPORTS_PER_SOL))
+        .deposit(new anchor.BN(10 * LAMPORTS_PER_SOL), null)
         .accounts({
           depositor: depositor1.publicKey,
           depositorRecord: depositor1RecordPda,
           protocolConfig: protocolConfigPda,
           vault: vaultPda,
+          shareMint: shareMintPda,
+          depositorShareAccount: depositor1ShareAccount,
+          position: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
           systemProgram: SystemProgram.programId,
         })
         .signers([depositor1])
@@ -997,16 +1266,18 @@ describe("solignition", () => {
       // 2. Request loan
       await program.methods
         .requestLoan(
-          new anchor.BN(integrationLoanId),
           new anchor.BN(3 * LAMPORTS_PER_SOL),
           new anchor.BN(1), // 1 second for quick test
           500,
-          100
+          100,
+          { simple: {} },
+          { act365: {} }
         )
         .accounts({
           borrower: borrower.publicKey,
           loan: integrationLoanPda,
           protocolConfig: protocolConfigPda,
+          termSchedule: termSchedulePda,
           vault: vaultPda,
           authorityPda: authorityPda,
           adminPda: adminPda,
@@ -1016,28 +1287,18 @@ describe("solignition", () => {
         .signers([borrower])
         .rpc();
 
-      // 3. Set deployed program
-      const programPubkey = Keypair.generate().publicKey;
-      await program.methods
-        .setDeployedProgram(new anchor.BN(integrationLoanId), programPubkey)
-        .accounts({
-          admin: admin.publicKey,
-          protocolConfig: protocolConfigPda,
-          loan: integrationLoanPda,
-        })
-        .signers([admin])
-        .rpc();
-
-      // 4. Wait for expiration
+      // 3. Wait for expiration
+      // The loan is never deployed: set_deployed_program needs a real program owned by authorityPda
       await new Promise(resolve => setTimeout(resolve, 2000));
 
-      // 5. Recover loan
+      // 4. Recover loan
       await program.methods
         .recoverLoan()
         .accounts({
           admin: admin.publicKey,
           protocolConfig: protocolConfigPda,
           loan: integrationLoanPda,
+          vault: vaultPda,
           adminPda: adminPda,
           treasury: treasuryPda,
           systemProgram: SystemProgram.programId,
@@ -1050,6 +1311,30 @@ describe("solignition", () => {
       assert.deepEqual(loan.state, { recovered: {} });
       assert.ok(loan.recoveredTs !== null);
 
+      // 5. Write off the unrecovered principal
+      const configBefore = await program.account.protocolConfig.fetch(protocolConfigPda);
+      await program.methods
+        .finalizeRecovery()
+        .accounts({
+          admin: admin.publicKey,
+          protocolConfig: protocolConfigPda,
+          loan: integrationLoanPda,
+        })
+        .signers([admin])
+        .rpc();
+
+      const writtenOff = await program.account.loan.fetch(integrationLoanPda);
+      assert.deepEqual(writtenOff.state, { writtenOff: {} });
+      const config = await program.account.protocolConfig.fetch(protocolConfigPda);
+      assert.equal(
+        config.totalLoansOutstanding.toNumber(),
+        configBefore.totalLoansOutstanding.toNumber() - loan.principalOutstanding.toNumber()
+      );
+      assert.equal(
+        config.totalRealizedLosses.toNumber(),
+        configBefore.totalRealizedLosses.toNumber() + loan.principalOutstanding.toNumber()
+      );
+
       console.log("✅ Full loan lifecycle completed successfully");
     });
   });
@@ -71,6 +71,7 @@ const TREASURY_SEED = Buffer.from('treasury');
 const LOAN_SEED = Buffer.from('loan');
 const DEPOSITOR_SEED = Buffer.from('depositor');
 const PROTOCOL_CONFIG_SEED = Buffer.from('config');
+const BPF_LOADER_UPGRADEABLE_PROGRAM_ID = new PublicKey('BPFLoaderUpgradeab1e11111111111111111111111');
 
 // ============ Types ============
 interface LoanRequestedEvent {
@@ -96,6 +97,7 @@ interface DeploymentRecord {
   bufferAccount?: string;
   deployTxSignature?: string;
   setDeployedTxSignature?: string;
+  settleTxSignature?: string;
   recoveryTxSignature?: string;
   status: 'pending' | 'deploying' | 'deployed' | 'recovering' | 'recovered' | 'failed';
   error?: string;
@@ -106,11 +108,24 @@ interface DeploymentRecord {
 }
 
 enum LoanState {
-  Active = 0,
-  Repaid = 1,
-  Recovered = 2,
+  PendingDeployment = 0,
+  Deployed = 1,
+  Overdue = 2,
+  Defaulted = 3,
+  Repaid = 4,
+  Recovered = 5,
+  Cancelled = 6,
+  Closed = 7,
+  WrittenOff = 8,
 }
 
+const OUTSTANDING_LOAN_STATES = [
+  LoanState.PendingDeployment,
+  LoanState.Deployed,
+  LoanState.Overdue,
+  LoanState.Defaulted,
+];
+
 // ============ Logging ============
 const logger = winston.createLogger({
   level: process.env.LOG_LEVEL || 'info',
@@ -412,12 +427,19 @@ class ProgramDeployer {
 
     try {
       const loanIdBn = new anchor.BN(loanId);
+      const [programData] = PublicKey.findProgramAddressSync(
+        [programPubkey.toBuffer()],
+        BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+      );
       const tx = await this.program.methods
-        .setDeployedProgram(loanIdBn, programPubkey)
+        .setDeployedProgram(loanIdBn)
         .accounts({
           admin: this.adminWallet.publicKey,
           protocolConfig: this.configPda,
           loan: this.getLoanPda(loanId),
+          program: programPubkey,
+          programData,
+          authorityPda: this.authorityPda,
         })
         .signers([this.adminWallet.payer])
         .rpc();
@@ -430,46 +452,75 @@ class ProgramDeployer {
     }
   }
 
-  async closeProgram(
+  async settleDeployment(loanId: string, programPubkey: PublicKey): Promise<string> {
+    try {
+      const [programData] = PublicKey.findProgramAddressSync(
+        [programPubkey.toBuffer()],
+        BPF_LOADER_UPGRADEABLE_PROGRAM_ID
+      );
+      const tx = await this.program.methods
+        .settleDeployment()
+        .accounts({
+          deployer: this.deployerWallet.publicKey,
+          protocolConfig: this.configPda,
+          loan: this.getLoanPda(loanId),
+          program: programPubkey,
+          programData,
+          vault: this.vaultPda,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([this.deployerWallet.payer])
+        .rpc();
+
+      logger.info('Settled deployment', { loanId, programPubkey: programPubkey.toBase58(), tx });
+      return tx;
+    } catch (error) {
+      logger.error('Failed to settle deployment', { loanId, error });
+      throw error;
+    }
+  }
+
+  async closeRecoveredProgram(
     loanId: string,
     programId: PublicKey
   ): Promise<{ reclaimedSol: number; signature: string }> {
     try {
       logger.info(`Closing program for loan ${loanId}`, { programId: programId.toBase58() });
 
-      const programAccountInfo = await this.connection.getAccountInfo(programId);
-      if (!programAccountInfo) {
-        throw new Error('Program account not found');
-      }
-
-      const programDataAddress = new PublicKey(programAccountInfo.data.slice(4, 36));
-
-      const closeIx = createCloseAccountInstruction(
-        programDataAddress,
-        this.deployerPda,
-        this.authorityPda,
-        programId
+      const [programData] = PublicKey.findProgramAddressSync(
+        [programId.toBuffer()],
+        BPF_LOADER_UPGRADEABLE_PROGRAM_ID
       );
+      const beforeBalance = await this.connection.getBalance(this.vaultPda);
 
-      const tx = new Transaction().add(closeIx);
-      const signature = await sendAndConfirmTransaction(
-        this.connection,
-        tx,
-        [this.deployerWallet.payer],
-        { commitment: 'confirmed' }
-      );
+      // The program closes itself into the vault, signed by its authority PDA
+      const signature = await this.program.methods
+        .closeRecoveredProgram()
+        .accounts({
+          caller: this.deployerWallet.publicKey,
+          protocolConfig: this.configPda,
+          loan: this.getLoanPda(loanId),
+          vault: this.vaultPda,
+          program: programId,
+          programData,
+          authorityPda: this.authorityPda,
+          bpfLoaderUpgradeable: BPF_LOADER_UPGRADEABLE_PROGRAM_ID,
+        })
+        .signers([this.deployerWallet.payer])
+        .rpc();
 
-      const afterBalance = await this.connection.getBalance(this.deployerPda);
+      const afterBalance = await this.connection.getBalance(this.vaultPda);
+      const reclaimedSol = (afterBalance - beforeBalance) / LAMPORTS_PER_SOL;
 
       logger.info('Program closed successfully', {
         loanId,
         programId: programId.toBase58(),
         signature,
-        reclaimedSol: afterBalance / LAMPORTS_PER_SOL,
+        reclaimedSol,
       });
 
       metrics.recoveryTotal.inc({ status: 'success' });
-      return { reclaimedSol: afterBalance / LAMPORTS_PER_SOL, signature };
+      return { reclaimedSol, signature };
     } catch (error) {
       logger.error('Failed to close program', { loanId, error });
       metrics.recoveryTotal.inc({ status: 'failure' });
@@ -477,6 +528,61 @@ class ProgramDeployer {
     }
   }
 
+  async recoverLoan(loanId: string): Promise<string> {
+    if (!this.adminWallet) {
+      throw new Error('Admin wallet not configured for recover_loan');
+    }
+
+    try {
+      const protocolConfig = await this.program.account['protocolConfig'].fetch(this.configPda);
+      const [adminPda] = PublicKey.findProgramAddressSync([ADMIN_SEED], config.programId);
+
+      const tx = await this.program.methods
+        .recoverLoan()
+        .accounts({
+          admin: this.adminWallet.publicKey,
+          protocolConfig: this.configPda,
+          loan: this.getLoanPda(loanId),
+          vault: this.vaultPda,
+          adminPda,
+          treasury: (protocolConfig as any).treasury as PublicKey,
+          systemProgram: SystemProgram.programId,
+        })
+        .signers([this.adminWallet.payer])
+        .rpc();
+
+      logger.info('Recovered loan', { loanId, tx });
+      return tx;
+    } catch (error) {
+      logger.error('Failed to recover loan', { loanId, error });
+      throw error;
+    }
+  }
+
+  async finalizeRecovery(loanId: string): Promise<string> {
+    if (!this.adminWallet) {
+      throw new Error('Admin wallet not configured for finalize_recovery');
+    }
+
+    try {
+      const tx = await this.program.methods
+        .finalizeRecovery()
+        .accounts({
+          admin: this.adminWallet.publicKey,
+          protocolConfig: this.configPda,
+          loan: this.getLoanPda(loanId),
+        })
+        .signers([this.adminWallet.payer])
+        .rpc();
+
+      logger.info('Finalized loan recovery', { loanId, tx });
+      return tx;
+    } catch (error) {
+      logger.error('Failed to finalize recovery', { loanId, error });
+      throw error;
+    }
+  }
+
   async returnReclaimedSol(loanId: string, amount: number): Promise<string> {
     try {
       const amountLamports = new anchor.BN(amount * LAMPORTS_PER_SOL);
@@ -657,6 +763,15 @@ class EventMonitor extends EventEmitter {
   private async checkExpiredLoans(): Promise<void> {
     const deployments = await this.stateManager.getAllDeployments();
     const activeLoans = deployments.filter(d => d.status === 'deployed');
+    if (activeLoans.length === 0) {
+      metrics.activeLoans.set(0);
+      return;
+    }
+
+    // recover_loan is rejected until the grace period after expiry has passed
+    const [configPda] = PublicKey.findProgramAddressSync([PROTOCOL_CONFIG_SEED], config.programId);
+    const protocolConfig = await this.program.account['protocolConfig'].fetch(configPda);
+    const gracePeriod = (protocolConfig as any).gracePeriod.toNumber();
 
     for (const deployment of activeLoans) {
       try {
@@ -666,9 +781,10 @@ class EventMonitor extends EventEmitter {
 
         const now = Date.now() / 1000;
         const expiry = (loan as any).startTs.toNumber() + (loan as any).duration.toNumber();
+        const state = decodeLoanState((loan as any).state);
 
-        if (now >= expiry && (loan as any).state === LoanState.Active) {
-          logger.info(`Loan ${deployment.loanId} has expired`);
+        if (now >= expiry + gracePeriod && OUTSTANDING_LOAN_STATES.includes(state)) {
+          logger.info(`Loan ${deployment.loanId} is past its grace period`);
           this.emit('loanExpired', { loanId: deployment.loanId });
         }
       } catch (error) {
@@ -809,6 +925,9 @@ class DeployerOrchestrator {
     const setTx = await this.programDeployer.setDeployedProgram(loanId, programId);
     deployment.setDeployedTxSignature = setTx;
 
+    // Return principal not locked up as program rent to the vault
+    deployment.settleTxSignature = await this.programDeployer.settleDeployment(loanId, programId);
+
     deployment.status = 'deployed';
     deployment.updatedAt = Date.now();
     await this.stateManager.saveDeployment(deployment);
@@ -826,6 +945,15 @@ class DeployerOrchestrator {
 
   private async handleLoanExpired(event: any): Promise<void> {
     const { loanId } = event;
+
+    // Mark the loan recovered on-chain before closing its program
+    try {
+      await this.programDeployer.recoverLoan(loanId);
+    } catch (error) {
+      logger.error('recover_loan failed, skipping recovery', { loanId, error });
+      return;
+    }
+
     await this.processRecovery(loanId);
   }
 
@@ -838,21 +966,27 @@ class DeployerOrchestrator {
       return;
     }
 
+    // close_recovered_program and finalize_recovery only accept recovered loans
+    const loan = await this.programDeployer.getLoanAccount(loanId);
+    const state = decodeLoanState(loan.state);
+    if (state !== LoanState.Recovered) {
+      logger.info(`Loan ${loanId} has not been recovered`, { state: LoanState[state] });
+      return;
+    }
+
     try {
       deployment.status = 'recovering';
       deployment.updatedAt = Date.now();
       await this.stateManager.saveDeployment(deployment);
 
       if (deployment.programId) {
-        const { reclaimedSol, signature } = await this.programDeployer.closeProgram(
+        const { reclaimedSol, signature } = await this.programDeployer.closeRecoveredProgram(
           loanId,
           new PublicKey(deployment.programId)
         );
 
-        const returnTx = await this.programDeployer.returnReclaimedSol(
-          loanId,
-          reclaimedSol
-        );
+        // Write off whatever principal the closed program didn't cover
+        await this.programDeployer.finalizeRecovery(loanId);
 
         deployment.recoveryTxSignature = signature;
         deployment.status = 'recovered';
@@ -947,6 +1081,12 @@ class HealthServer {
 }
 
 // ============ Helper Functions ============
+// Anchor decodes enum variants as `{ variantName: {} }`
+function decodeLoanState(state: Record<string, unknown>): LoanState {
+  const variant = Object.keys(state)[0];
+  return LoanState[(variant.charAt(0).toUpperCase() + variant.slice(1)) as keyof typeof LoanState];
+}
+
 function createInitializeBufferInstruction(
   buffer: PublicKey,
   authority: PublicKey
@@ -1025,29 +1165,6 @@ function createDeployWithMaxDataLenInstruction(
   });
 }
 
-function createCloseAccountInstruction(
-  account: PublicKey,
-  recipient: PublicKey,
-  authority: PublicKey,
-  program?: PublicKey
-): TransactionInstruction {
-  const keys = [
-    { pubkey: account, isSigner: false, isWritable: true },
-    { pubkey: recipient, isSigner: false, isWritable: true },
-    { pubkey: authority, isSigner: true, isWritable: false },
-  ];
-
-  if (program) {
-    keys.push({ pubkey: program, isSigner: false, isWritable: true });
-  }
-
-  return new TransactionInstruction({
-    keys,
-    programId: BPF_LOADER_DEPRECATED_PROGRAM_ID,
-    data: Buffer.from([4]),
-  });
-}
-
 // ============ Main Application ============
 async function main() {
   logger.info('Starting Solana Lending Protocol Deployer Service', { 
@@ -3,6 +3,7 @@ import { UiWalletAccount, useWalletUiSigner } from '@wallet-ui/react'
 import { useWalletUiSignAndSend } from '@wallet-ui/react-gill'
 import { getDepositInstructionAsync, SOLIGNITION_PROGRAM_ADDRESS } from '@project/anchor'
 import { getProgramDerivedAddress } from '@solana/kit'
+import { getAssociatedTokenAddress } from 'gill/programs'
 import { toastTx } from '@/components/toast-tx'
 import { useSolana } from '@/components/solana/use-solana'
 
@@ -20,10 +21,20 @@ export function useDepositMutation({ account }: { account: UiWalletAccount }) {
         seeds: [new TextEncoder().encode('config')],
       })
 
+      // Derive the vault share mint and the depositor's share token account
+      const [shareMint] = await getProgramDerivedAddress({
+        programAddress: SOLIGNITION_PROGRAM_ADDRESS,
+        seeds: [new TextEncoder().encode('share_mint')],
+      })
+      const depositorShareAccount = await getAssociatedTokenAddress(shareMint, signer.address)
+
       const instruction = await getDepositInstructionAsync({
         depositor: signer,
         protocolConfig,
+        shareMint,
+        depositorShareAccount,
         amount,
+        lockDuration: null,
       })
 
       return await signAndSend(instruction, signer)
@@ -1,16 +1,19 @@
 import { useMutation, useQueryClient } from '@tanstack/react-query'
 import { UiWalletAccount, useWalletUiSigner } from '@wallet-ui/react'
 import { useWalletUiSignAndSend } from '@wallet-ui/react-gill'
-import { getInitializeInstructionAsync } from '@project/anchor'
+import { getInitializeInstructionAsync, type RateModelArgs } from '@project/anchor'
 import { toastTx } from '@/components/toast-tx'
 import { useSolana } from '@/components/solana/use-solana'
 import type { Address } from '@solana/kit'
 
 type InitializeParams = {
   adminFeeSplitBps: number
-  defaultInterestRateBps: number
+  rateModel: RateModelArgs
   defaultAdminFeeBps: number
   deployer: Address
+  shareName: string
+  shareSymbol: string
+  shareUri: string
 }
 
 export function useInitializeProtocolMutation({ account }: { account: UiWalletAccount }) {
@@ -25,8 +28,11 @@ export function useInitializeProtocolMutation({ account }: { account: UiWalletAc
         admin: signer,
         deployer: params.deployer,
         adminFeeSplitBps: params.adminFeeSplitBps,
-        defaultInterestRateBps: params.defaultInterestRateBps,
+        rateModel: params.rateModel,
         defaultAdminFeeBps: params.defaultAdminFeeBps,
+        shareName: params.shareName,
+        shareSymbol: params.shareSymbol,
+        shareUri: params.shareUri,
       })
 
       return await signAndSend(instruction, signer)
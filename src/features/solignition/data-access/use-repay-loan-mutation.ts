@@ -2,7 +2,7 @@ import { useMutation, useQueryClient } from '@tanstack/react-query'
 import { UiWalletAccount, useWalletUiSigner } from '@wallet-ui/react'
 import { useWalletUiSignAndSend } from '@wallet-ui/react-gill'
 import { getRepayLoanInstructionAsync, SOLIGNITION_PROGRAM_ADDRESS } from '@project/anchor'
-import { getProgramDerivedAddress } from '@solana/kit'
+import { address, getAddressEncoder, getProgramDerivedAddress } from '@solana/kit'
 import { toastTx } from '@/components/toast-tx'
 import { useSolana } from '@/components/solana/use-solana'
 import type { Address } from '@solana/kit'
@@ -14,18 +14,31 @@ export function useRepayLoanMutation({ account }: { account: UiWalletAccount })
   const signAndSend = useWalletUiSignAndSend()
 
   return useMutation({
-    mutationFn: async ({ loanAddress, programData }: { loanAddress: Address; programData: Address }) => {
+    mutationFn: async ({ loanAddress, programPubkey }: { loanAddress: Address; programPubkey: Address }) => {
       // Derive protocol config PDA
       const [protocolConfig] = await getProgramDerivedAddress({
         programAddress: SOLIGNITION_PROGRAM_ADDRESS,
         seeds: [new TextEncoder().encode('config')],
       })
 
+      // Derive admin fee PDA, which refunds unused admin fee on early repayment
+      const [adminPda] = await getProgramDerivedAddress({
+        programAddress: SOLIGNITION_PROGRAM_ADDRESS,
+        seeds: [new TextEncoder().encode('admin')],
+      })
+
+      // Derive the upgradeable loader's program data account
+      const [programData] = await getProgramDerivedAddress({
+        programAddress: address('BPFLoaderUpgradeab1e11111111111111111111111'),
+        seeds: [getAddressEncoder().encode(programPubkey)],
+      })
+
       const instruction = await getRepayLoanInstructionAsync({
         borrower: signer,
         loan: loanAddress,
         protocolConfig,
         programData,
+        adminPda,
       })
 
       return await signAndSend(instruction, signer)
@@ -1,7 +1,13 @@
 import { useMutation, useQueryClient } from '@tanstack/react-query'
 import { UiWalletAccount, useWalletUiSigner } from '@wallet-ui/react'
 import { useWalletUiSignAndSend } from '@wallet-ui/react-gill'
-import { getRequestLoanInstructionAsync, SOLIGNITION_PROGRAM_ADDRESS } from '@project/anchor'
+import {
+  DayCount,
+  getLoanAddress,
+  getRequestLoanInstructionAsync,
+  InterestMode,
+  SOLIGNITION_PROGRAM_ADDRESS,
+} from '@project/anchor'
 import { getProgramDerivedAddress } from '@solana/kit'
 import { toastTx } from '@/components/toast-tx'
 import { useSolana } from '@/components/solana/use-solana'
@@ -10,8 +16,10 @@ import { useProtocolConfig } from './use-protocol-config'
 type RequestLoanParams = {
   principal: bigint
   duration: bigint
-  interestRateBps: number
-  adminFeeBps: number
+  maxInterestRateBps: number
+  maxAdminFeeBps: number
+  interestMode?: InterestMode
+  dayCount?: DayCount
 }
 
 export function useRequestLoanMutation({ account }: { account: UiWalletAccount }) {
@@ -27,7 +35,8 @@ export function useRequestLoanMutation({ account }: { account: UiWalletAccount }
         throw new Error('Protocol config not loaded')
       }
 
-      const loanId = protocolConfigQuery.data.data.loanCounter
+      // The program assigns the next ID from the on-chain counter
+      const loan = await getLoanAddress({ loanId: protocolConfigQuery.data.data.loanCounter })
 
       // Derive protocol config PDA
       const [protocolConfig] = await getProgramDerivedAddress({
@@ -35,21 +44,17 @@ export function useRequestLoanMutation({ account }: { account: UiWalletAccount }
         seeds: [new TextEncoder().encode('config')],
       })
 
-      // Derive deployer PDA
-      const [deployerPda] = await getProgramDerivedAddress({
-        programAddress: SOLIGNITION_PROGRAM_ADDRESS,
-        seeds: [new TextEncoder().encode('deployer')],
-      })
-
       const instruction = await getRequestLoanInstructionAsync({
         borrower: signer,
         protocolConfig,
-        deployerPda,
-        loanId,
+        deployerPda: protocolConfigQuery.data.data.deployer,
+        loan,
         principal: params.principal,
         duration: params.duration,
-        interestRateBps: params.interestRateBps,
-        adminFeeBps: params.adminFeeBps,
+        maxInterestRateBps: params.maxInterestRateBps,
+        maxAdminFeeBps: params.maxAdminFeeBps,
+        interestMode: params.interestMode ?? InterestMode.Simple,
+        dayCount: params.dayCount ?? DayCount.Act365,
       })
 
       return await signAndSend(instruction, signer)
@@ -2,7 +2,7 @@ import { useMutation, useQueryClient } from '@tanstack/react-query'
 import { UiWalletAccount, useWalletUiSigner } from '@wallet-ui/react'
 import { useWalletUiSignAndSend } from '@wallet-ui/react-gill'
 import { getSetDeployedProgramInstruction, SOLIGNITION_PROGRAM_ADDRESS } from '@project/anchor'
-import { getProgramDerivedAddress } from '@solana/kit'
+import { address, getAddressEncoder, getProgramDerivedAddress } from '@solana/kit'
 import { toastTx } from '@/components/toast-tx'
 import { useSolana } from '@/components/solana/use-solana'
 import type { Address } from '@solana/kit'
@@ -27,12 +27,26 @@ export function useSetDeployedProgramMutation({ account }: { account: UiWalletAc
         seeds: [new TextEncoder().encode('loan'), new Uint8Array(new BigUint64Array([loanId]).buffer)],
       })
 
+      // Derive the upgradeable loader's program data account
+      const [programData] = await getProgramDerivedAddress({
+        programAddress: address('BPFLoaderUpgradeab1e11111111111111111111111'),
+        seeds: [getAddressEncoder().encode(programPubkey)],
+      })
+
+      // Derive authority PDA
+      const [authorityPda] = await getProgramDerivedAddress({
+        programAddress: SOLIGNITION_PROGRAM_ADDRESS,
+        seeds: [new TextEncoder().encode('authority')],
+      })
+
       const instruction = getSetDeployedProgramInstruction({
         admin: signer,
         protocolConfig,
         loan: loanAddress,
+        program: programPubkey,
+        programData,
+        authorityPda,
         loanId,
-        programPubkey,
       })
 
       return await signAndSend(instruction, signer)
@@ -9,10 +9,21 @@ import type { Address } from '@solana/kit'
 
 type UpdateConfigParams = {
   adminFeeSplitBps?: number
-  defaultInterestRateBps?: number
   defaultAdminFeeBps?: number
   deployer?: Address
   treasury?: Address
+  solvencyToleranceLamports?: bigint
+  lendingPaused?: boolean
+  maxTotalDeposits?: bigint
+  maxDepositPerWallet?: bigint
+  minDepositAmount?: bigint
+  gracePeriod?: bigint
+  penaltyRateBps?: number
+  maxLoanTerm?: bigint
+  minInterestPeriod?: bigint
+  earlyRepayFeeRefund?: boolean
+  deploymentDeadline?: bigint
+  cancelFeeRefundBps?: number
 }
 
 export function useUpdateConfigMutation({ account }: { account: UiWalletAccount }) {
@@ -33,10 +44,21 @@ export function useUpdateConfigMutation({ account }: { account: UiWalletAccount
         admin: signer,
         protocolConfig,
         adminFeeSplitBps: params.adminFeeSplitBps ?? null,
-        defaultInterestRateBps: params.defaultInterestRateBps ?? null,
         defaultAdminFeeBps: params.defaultAdminFeeBps ?? null,
         deployer: params.deployer ?? null,
         treasury: params.treasury ?? null,
+        solvencyToleranceLamports: params.solvencyToleranceLamports ?? null,
+        lendingPaused: params.lendingPaused ?? null,
+        maxTotalDeposits: params.maxTotalDeposits ?? null,
+        maxDepositPerWallet: params.maxDepositPerWallet ?? null,
+        minDepositAmount: params.minDepositAmount ?? null,
+        gracePeriod: params.gracePeriod ?? null,
+        penaltyRateBps: params.penaltyRateBps ?? null,
+        maxLoanTerm: params.maxLoanTerm ?? null,
+        minInterestPeriod: params.minInterestPeriod ?? null,
+        earlyRepayFeeRefund: params.earlyRepayFeeRefund ?? null,
+        deploymentDeadline: params.deploymentDeadline ?? null,
+        cancelFeeRefundBps: params.cancelFeeRefundBps ?? null,
       })
 
       return await signAndSend(instruction, signer)
@@ -3,6 +3,7 @@ import { UiWalletAccount, useWalletUiSigner } from '@wallet-ui/react'
 import { useWalletUiSignAndSend } from '@wallet-ui/react-gill'
 import { getWithdrawInstructionAsync, SOLIGNITION_PROGRAM_ADDRESS } from '@project/anchor'
 import { getProgramDerivedAddress } from '@solana/kit'
+import { getAssociatedTokenAddress } from 'gill/programs'
 import { toastTx } from '@/components/toast-tx'
 import { useSolana } from '@/components/solana/use-solana'
 
@@ -13,17 +14,27 @@ export function useWithdrawMutation({ account }: { account: UiWalletAccount }) {
   const signAndSend = useWalletUiSignAndSend()
 
   return useMutation({
-    mutationFn: async (amount: bigint) => {
+    mutationFn: async (shares: bigint) => {
       // Derive protocol config PDA
       const [protocolConfig] = await getProgramDerivedAddress({
         programAddress: SOLIGNITION_PROGRAM_ADDRESS,
         seeds: [new TextEncoder().encode('config')],
       })
 
+      // Derive the vault share mint and the depositor's share token account
+      const [shareMint] = await getProgramDerivedAddress({
+        programAddress: SOLIGNITION_PROGRAM_ADDRESS,
+        seeds: [new TextEncoder().encode('share_mint')],
+      })
+      const depositorShareAccount = await getAssociatedTokenAddress(shareMint, signer.address)
+
       const instruction = await getWithdrawInstructionAsync({
         depositor: signer,
         protocolConfig,
-        amount,
+        shareMint,
+        depositorShareAccount,
+        shares,
+        closeRecord: false,
       })
 
       return await signAndSend(instruction, signer)
@@ -10,7 +10,6 @@ import { address } from '@solana/kit'
 
 export function AdminConfigPanel({ account }: { account: UiWalletAccount }) {
   const [adminFeeSplit, setAdminFeeSplit] = useState('')
-  const [defaultInterestRate, setDefaultInterestRate] = useState('')
   const [defaultAdminFee, setDefaultAdminFee] = useState('')
   const [deployerAddress, setDeployerAddress] = useState('')
   const [treasuryAddress, setTreasuryAddress] = useState('')
@@ -24,9 +23,6 @@ export function AdminConfigPanel({ account }: { account: UiWalletAccount }) {
     if (adminFeeSplit) {
       params.adminFeeSplitBps = Math.floor(parseFloat(adminFeeSplit) * 100)
     }
-    if (defaultInterestRate) {
-      params.defaultInterestRateBps = Math.floor(parseFloat(defaultInterestRate) * 100)
-    }
     if (defaultAdminFee) {
       params.defaultAdminFeeBps = Math.floor(parseFloat(defaultAdminFee) * 100)
     }
@@ -43,14 +39,13 @@ export function AdminConfigPanel({ account }: { account: UiWalletAccount }) {
 
     // Reset form
     setAdminFeeSplit('')
-    setDefaultInterestRate('')
     setDefaultAdminFee('')
     setDeployerAddress('')
     setTreasuryAddress('')
   }
 
   const hasChanges =
-    adminFeeSplit || defaultInterestRate || defaultAdminFee || deployerAddress || treasuryAddress
+    adminFeeSplit || defaultAdminFee || deployerAddress || treasuryAddress
 
   if (configQuery.isLoading) {
     return (
@@ -92,8 +87,8 @@ export function AdminConfigPanel({ account }: { account: UiWalletAccount }) {
             <p className="text-lg font-semibold">{(config.adminFeeSplitBps / 100).toFixed(1)}%</p>
           </div>
           <div>
-            <p className="text-sm text-muted-foreground">Default Interest Rate</p>
-            <p className="text-lg font-semibold">{(config.defaultInterestRateBps / 100).toFixed(1)}%</p>
+            <p className="text-sm text-muted-foreground">Base Interest Rate</p>
+            <p className="text-lg font-semibold">{(config.rateModel.baseRateBps / 100).toFixed(1)}%</p>
           </div>
           <div>
             <p className="text-sm text-muted-foreground">Default Admin Fee</p>
@@ -136,20 +131,6 @@ export function AdminConfigPanel({ account }: { account: UiWalletAccount }) {
             />
           </div>
 
-          <div className="space-y-2">
-            <Label htmlFor="update-default-interest">Default Interest Rate (%)</Label>
-            <Input
-              id="update-default-interest"
-              type="number"
-              step="0.1"
-              min="0"
-              placeholder={(config.defaultInterestRateBps / 100).toFixed(1)}
-              value={defaultInterestRate}
-              onChange={(e) => setDefaultInterestRate(e.target.value)}
-              disabled={updateConfigMutation.isPending}
-            />
-          </div>
-
           <div className="space-y-2">
             <Label htmlFor="update-default-admin-fee">Default Admin Fee (%)</Label>
             <Input
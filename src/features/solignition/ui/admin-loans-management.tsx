@@ -9,7 +9,7 @@ import { AppExplorerLink } from '@/components/app-explorer-link'
 import { useLoans } from '../data-access/use-loans'
 import { useRecoverLoanMutation } from '../data-access/use-recover-loan-mutation'
 import { useSetDeployedProgramMutation } from '../data-access/use-set-deployed-program-mutation'
-import { LoanState } from '@project/anchor'
+import { LoanState, OUTSTANDING_LOAN_STATES } from '@project/anchor'
 import { address } from '@solana/kit'
 
 export function AdminLoansManagement({ account }: { account: UiWalletAccount }) {
@@ -30,12 +30,24 @@ export function AdminLoansManagement({ account }: { account: UiWalletAccount })
 
   const getLoanStateBadge = (state: number) => {
     switch (state) {
-      case LoanState.Active:
-        return <Badge className="bg-green-500">Active</Badge>
+      case LoanState.PendingDeployment:
+        return <Badge variant="outline">Pending Deployment</Badge>
+      case LoanState.Deployed:
+        return <Badge className="bg-green-500">Deployed</Badge>
+      case LoanState.Overdue:
+        return <Badge className="bg-yellow-500">Overdue</Badge>
+      case LoanState.Defaulted:
+        return <Badge className="bg-orange-500">Defaulted</Badge>
       case LoanState.Repaid:
         return <Badge className="bg-blue-500">Repaid</Badge>
       case LoanState.Recovered:
         return <Badge variant="destructive">Recovered</Badge>
+      case LoanState.Cancelled:
+        return <Badge variant="secondary">Cancelled</Badge>
+      case LoanState.Closed:
+        return <Badge variant="secondary">Closed</Badge>
+      case LoanState.WrittenOff:
+        return <Badge variant="destructive">Written Off</Badge>
       default:
         return <Badge variant="outline">Unknown</Badge>
     }
@@ -80,7 +92,7 @@ export function AdminLoansManagement({ account }: { account: UiWalletAccount })
     )
   }
 
-  const activeLoans = loansQuery.data?.filter((loan) => loan.data.state === LoanState.Active) || []
+  const activeLoans = loansQuery.data?.filter((loan) => OUTSTANDING_LOAN_STATES.includes(loan.data.state)) || []
   const expiredLoans = activeLoans.filter(isLoanExpired)
 
   return (
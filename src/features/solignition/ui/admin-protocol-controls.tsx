@@ -10,9 +10,19 @@ import { useInitializeProtocolMutation } from '../data-access/use-initialize-mut
 import { useSetPausedMutation } from '../data-access/use-set-paused-mutation'
 import { address } from '@solana/kit'
 
+// Metaplex metadata for the vault share token
+const SHARE_NAME = 'Solignition Vault Share'
+const SHARE_SYMBOL = 'sSOL'
+const SHARE_URI = ''
+
+// Rate curve above the base rate; tune later with update_rate_model
+const SLOPE1_BPS = 400
+const OPTIMAL_UTILIZATION_BPS = 8000
+const SLOPE2_BPS = 6000
+
 export function AdminProtocolControls({ account }: { account: UiWalletAccount }) {
   const [adminFeeSplit, setAdminFeeSplit] = useState('50')
-  const [defaultInterestRate, setDefaultInterestRate] = useState('5')
+  const [baseInterestRate, setBaseInterestRate] = useState('5')
   const [defaultAdminFee, setDefaultAdminFee] = useState('1')
   const [deployerAddress, setDeployerAddress] = useState('')
 
@@ -25,9 +35,17 @@ export function AdminProtocolControls({ account }: { account: UiWalletAccount })
 
     await initializeMutation.mutateAsync({
       adminFeeSplitBps: Math.floor(parseFloat(adminFeeSplit) * 100),
-      defaultInterestRateBps: Math.floor(parseFloat(defaultInterestRate) * 100),
+      rateModel: {
+        baseRateBps: Math.floor(parseFloat(baseInterestRate) * 100),
+        slope1Bps: SLOPE1_BPS,
+        optimalUtilizationBps: OPTIMAL_UTILIZATION_BPS,
+        slope2Bps: SLOPE2_BPS,
+      },
       defaultAdminFeeBps: Math.floor(parseFloat(defaultAdminFee) * 100),
       deployer: address(deployerAddress),
+      shareName: SHARE_NAME,
+      shareSymbol: SHARE_SYMBOL,
+      shareUri: SHARE_URI,
     })
   }
 
@@ -78,15 +96,15 @@ export function AdminProtocolControls({ account }: { account: UiWalletAccount })
             </div>
 
             <div className="space-y-2">
-              <Label htmlFor="default-interest">Default Interest Rate (%)</Label>
+              <Label htmlFor="base-interest">Base Interest Rate (%)</Label>
               <Input
-                id="default-interest"
+                id="base-interest"
                 type="number"
                 step="0.1"
                 min="0"
                 placeholder="5"
-                value={defaultInterestRate}
-                onChange={(e) => setDefaultInterestRate(e.target.value)}
+                value={baseInterestRate}
+                onChange={(e) => setBaseInterestRate(e.target.value)}
                 disabled={initializeMutation.isPending}
               />
             </div>
@@ -10,7 +10,7 @@ import { useWithdrawMutation } from '../data-access/use-withdraw-mutation'
 
 export function DepositWithdrawPanel({ account }: { account: UiWalletAccount }) {
   const [depositAmount, setDepositAmount] = useState('')
-  const [withdrawAmount, setWithdrawAmount] = useState('')
+  const [withdrawShares, setWithdrawShares] = useState('')
 
   const depositorQuery = useDepositorRecord(account.address)
   const depositMutation = useDepositMutation({ account })
@@ -29,11 +29,12 @@ export function DepositWithdrawPanel({ account }: { account: UiWalletAccount })
   }
 
   const handleWithdraw = async () => {
-    const amount = parseFloat(withdrawAmount)
-    if (isNaN(amount) || amount <= 0) return
+    const shares = parseFloat(withdrawShares)
+    if (isNaN(shares) || shares <= 0) return
 
-    await withdrawMutation.mutateAsync(BigInt(Math.floor(amount * 1_000_000_000)))
-    setWithdrawAmount('')
+    // Shares use the same 9 decimals as lamports
+    await withdrawMutation.mutateAsync(BigInt(Math.floor(shares * 1_000_000_000)))
+    setWithdrawShares('')
   }
 
   return (
@@ -55,7 +56,7 @@ export function DepositWithdrawPanel({ account }: { account: UiWalletAccount })
               </div>
               <div>
                 <p className="text-sm text-muted-foreground">Share Amount</p>
-                <p className="text-lg">{depositorQuery.data.data.shareAmount.toString()}</p>
+                <p className="text-lg">{formatSOL(depositorQuery.data.data.shareAmount)}</p>
               </div>
             </div>
           ) : (
@@ -93,21 +94,21 @@ export function DepositWithdrawPanel({ account }: { account: UiWalletAccount })
 
           {/* Withdraw */}
           <div className="space-y-2">
-            <Label htmlFor="withdraw-amount">Withdraw Amount (SOL)</Label>
+            <Label htmlFor="withdraw-shares">Withdraw Shares</Label>
             <div className="flex gap-2">
               <Input
-                id="withdraw-amount"
+                id="withdraw-shares"
                 type="number"
                 step="0.1"
                 min="0"
                 placeholder="0.0"
-                value={withdrawAmount}
-                onChange={(e) => setWithdrawAmount(e.target.value)}
+                value={withdrawShares}
+                onChange={(e) => setWithdrawShares(e.target.value)}
                 disabled={withdrawMutation.isPending || !depositorQuery.data}
               />
               <Button
                 onClick={handleWithdraw}
-                disabled={withdrawMutation.isPending || !withdrawAmount || !depositorQuery.data}
+                disabled={withdrawMutation.isPending || !withdrawShares || !depositorQuery.data}
                 variant="outline"
               >
                 {withdrawMutation.isPending ? 'Withdrawing...' : 'Withdraw'}
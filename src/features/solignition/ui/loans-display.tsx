@@ -5,7 +5,7 @@ import { Button } from '@/components/ui/button'
 import { AppExplorerLink } from '@/components/app-explorer-link'
 import { useLoansByBorrower } from '../data-access/use-loans'
 import { useRepayLoanMutation } from '../data-access/use-repay-loan-mutation'
-import { LoanState } from '@project/anchor'
+import { LoanState, OUTSTANDING_LOAN_STATES } from '@project/anchor'
 
 export function LoansDisplay({ account }: { account: UiWalletAccount }) {
   const loansQuery = useLoansByBorrower(account.address)
@@ -21,12 +21,24 @@ export function LoansDisplay({ account }: { account: UiWalletAccount }) {
 
   const getLoanStateBadge = (state: number) => {
     switch (state) {
-      case LoanState.Active:
-        return <Badge className="bg-green-500">Active</Badge>
+      case LoanState.PendingDeployment:
+        return <Badge variant="outline">Pending Deployment</Badge>
+      case LoanState.Deployed:
+        return <Badge className="bg-green-500">Deployed</Badge>
+      case LoanState.Overdue:
+        return <Badge className="bg-yellow-500">Overdue</Badge>
+      case LoanState.Defaulted:
+        return <Badge className="bg-orange-500">Defaulted</Badge>
       case LoanState.Repaid:
         return <Badge className="bg-blue-500">Repaid</Badge>
       case LoanState.Recovered:
         return <Badge variant="destructive">Recovered</Badge>
+      case LoanState.Cancelled:
+        return <Badge variant="secondary">Cancelled</Badge>
+      case LoanState.Closed:
+        return <Badge variant="secondary">Closed</Badge>
+      case LoanState.WrittenOff:
+        return <Badge variant="destructive">Written Off</Badge>
       default:
         return <Badge variant="outline">Unknown</Badge>
     }
@@ -76,7 +88,7 @@ export function LoansDisplay({ account }: { account: UiWalletAccount }) {
       <div className="grid gap-4">
         {loansQuery.data.map((loan) => {
           const totalOwed = calculateTotalOwed(loan.data.principal, loan.data.interestRateBps)
-          const isActive = loan.data.state === LoanState.Active
+          const isActive = OUTSTANDING_LOAN_STATES.includes(loan.data.state)
 
           return (
             <Card key={loan.address}>
@@ -96,7 +108,7 @@ export function LoansDisplay({ account }: { account: UiWalletAccount }) {
                       onClick={() =>
                         repayMutation.mutateAsync({
                           loanAddress: loan.address,
-                          programData: loan.data.programPubkey,
+                          programPubkey: loan.data.programPubkey,
                         })
                       }
                       disabled={repayMutation.isPending}
@@ -31,8 +31,8 @@ export function RequestLoanPanel({ account }: { account: UiWalletAccount }) {
     await requestLoanMutation.mutateAsync({
       principal: BigInt(Math.floor(principalAmount * 1_000_000_000)),
       duration: durationSeconds,
-      interestRateBps,
-      adminFeeBps,
+      maxInterestRateBps: interestRateBps,
+      maxAdminFeeBps: adminFeeBps,
     })
 
     // Reset form